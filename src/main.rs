@@ -1,7 +1,14 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
-use futures::TryStreamExt;
-use serde::{Serialize, Serializer};
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
 
 use iota_streams::app::message::LinkedMessage;
 use iota_streams::app::transport::tangle::{TangleAddress, TangleMessage};
@@ -14,8 +21,10 @@ type Result<T> = std::result::Result<T, anyhow::Error>;
 async fn main() -> Result<()> {
     let mut immudb_client = ImmuDB::new();
     immudb_client.login().await?;
+    let mut immudb_reader = immudb_client.clone();
+    let transport = CachingTransport::new(immudb_client);
     let author_seed = "author seed 2";
-    let mut author = Author::new(author_seed, ChannelType::MultiBranch, immudb_client.clone());
+    let mut author = Author::new(author_seed, ChannelType::MultiBranch, transport.clone());
     println!("Created Author {author}");
     let announcement_link = author.announcement_link().unwrap();
     let is_new = author.send_announce().await.is_ok();
@@ -24,7 +33,7 @@ async fn main() -> Result<()> {
             author_seed,
             &announcement_link,
             ChannelType::MultiBranch,
-            immudb_client.clone(),
+            transport.clone(),
         )
         .await?;
     }
@@ -32,7 +41,7 @@ async fn main() -> Result<()> {
     println!("freshly created: {is_new}");
     let num_msgs = author.sync_state().await?;
     println!("Synchronized {num_msgs} messages");
-    let mut subscriber = Subscriber::new("subscriber seed", immudb_client);
+    let mut subscriber = Subscriber::new("subscriber seed", transport.clone());
     println!("Created Subscriber {subscriber}");
     subscriber.receive_announcement(&announcement_link).await?;
     println!("Subscriber received announcement");
@@ -47,19 +56,34 @@ async fn main() -> Result<()> {
         .send_signed_packet(&keyload_link, &b"".into(), &b"test branch".into())
         .await?;
     println!("Author sent signed packet {last_msg_link}");
+    let mut msg_links = Vec::with_capacity(100);
     for x in 0u8..100 {
         let (msg_link, _) = author
             .send_signed_packet(&last_msg_link, &b"".into(), &x.to_ne_bytes().into())
             .await?;
+        msg_links.push(msg_link);
         last_msg_link = msg_link;
     }
     println!("Author sent 100 other messages");
+    transport.prefetch(&msg_links).await?;
+    println!("Prefetched {} messages concurrently", msg_links.len());
     let mut messages = subscriber.messages();
     let empty_payload = b"empty".into();
     while let Some(msg) = messages.try_next().await? {
         let payload = msg.body.masked_payload().unwrap_or(&empty_payload);
         println!("Subscriber received masked payload {payload}")
     }
+
+    let mut watched = immudb_reader.subscribe(msg_links.clone()).await?;
+    let mut watched_count = 0;
+    while watched.next().await.is_some() {
+        watched_count += 1;
+    }
+    println!("Subscribe stream delivered {watched_count} of {} watched links", msg_links.len());
+
+    let branch_messages = immudb_reader.recv_branch(&announcement_link).await?;
+    println!("Scanned {} messages from the branch via recv_branch", branch_messages.len());
+
     Ok(())
 }
 
@@ -87,19 +111,51 @@ impl Transport<TangleAddress, TangleMessage> for DummyTransport {
     }
 }
 
+fn build_url(path: &str) -> String {
+    let domain = "http://127.0.0.1:3323";
+    format!("{domain}{path}")
+}
+
+/// Body of a single request against immudb.
+enum ActorRequestBody {
+    Get,
+    Json(serde_json::Value),
+}
+
+/// Pooled, re-authenticating immudb client; clones share a token and only
+/// serialize against each other during re-login.
 #[derive(Clone, Debug)]
-struct ImmuDB {
+struct DbActorHandle {
     client: reqwest::Client,
+    token: Arc<tokio::sync::RwLock<Option<String>>>,
+    relogin: Arc<Mutex<()>>,
 }
 
-impl ImmuDB {
+impl DbActorHandle {
     fn new() -> Self {
         Self {
             client: reqwest::Client::new(),
+            token: Arc::new(tokio::sync::RwLock::new(None)),
+            relogin: Arc::new(Mutex::new(())),
         }
     }
 
-    async fn login(&mut self) -> Result<()> {
+    async fn issue(&self, path: &str, body: &ActorRequestBody) -> Result<reqwest::Response> {
+        let request = match body {
+            ActorRequestBody::Get => self.client.get(build_url(path)),
+            ActorRequestBody::Json(json) => self.client.post(build_url(path)).json(json),
+        };
+        let token = self.token.read().await.clone();
+        let request = match &token {
+            Some(token) => request
+                .header("Authorization", format!("Bearer {token}"))
+                .header("grpc-metadata-sessionid", token),
+            None => request,
+        };
+        Ok(request.send().await?)
+    }
+
+    async fn login(&self) -> Result<()> {
         #[derive(Serialize)]
         struct TokenRequest {
             #[serde(serialize_with = "serialize_to_base64")]
@@ -107,25 +163,101 @@ impl ImmuDB {
             #[serde(serialize_with = "serialize_to_base64")]
             password: String,
         }
-        self.client
-            .post(self.build_url("/login"))
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: String,
+        }
+
+        // Other requests keep running concurrently against the stale
+        // token while we wait for this lock; only other logins queue up.
+        let _guard = self.relogin.lock().await;
+
+        *self.token.write().await = None;
+        let response = self
+            .client
+            .post(build_url("/login"))
             .json(&TokenRequest {
                 user: String::from("immudb"),
                 password: String::from("immudb"),
             })
             .send()
             .await?;
-        self.client
-            .get(self.build_url("/db/use/defaultdb"))
-            .send()
-            .await?;
+        *self.token.write().await = Some(response.json::<TokenResponse>().await?.token);
+
+        // Scoping the session to a db hands back its own token.
+        let response = self.issue("/db/use/defaultdb", &ActorRequestBody::Get).await?;
+        *self.token.write().await = Some(response.json::<TokenResponse>().await?.token);
 
         Ok(())
     }
 
-    fn build_url(&self, path: &str) -> String {
-        let domain = "http://127.0.0.1:3323";
-        format!("{domain}{path}")
+    async fn post(&self, path: &'static str, body: serde_json::Value) -> Result<reqwest::Response> {
+        self.request(path, ActorRequestBody::Json(body)).await
+    }
+
+    async fn request(&self, path: &'static str, body: ActorRequestBody) -> Result<reqwest::Response> {
+        let mut result = self.issue(path, &body).await;
+        let needs_relogin = matches!(&result, Ok(response) if response.status() == reqwest::StatusCode::UNAUTHORIZED);
+        if needs_relogin && self.login().await.is_ok() {
+            result = self.issue(path, &body).await;
+        }
+        result
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ImmuDB {
+    db: DbActorHandle,
+    verified_state: Arc<Mutex<Option<VerifiedState>>>,
+}
+
+impl ImmuDB {
+    fn new() -> Self {
+        Self {
+            db: DbActorHandle::new(),
+            verified_state: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    async fn login(&mut self) -> Result<()> {
+        self.db.login().await
+    }
+
+    /// Verifies a verified set/get response's inclusion and consistency
+    /// proofs, then advances the trusted state if both hold. A fresh
+    /// `ImmuDB` has no prior root to chain from, so its first call is
+    /// trusted on first use.
+    async fn accept_verified(&self, key: &[u8], value: &[u8], proof: VerifiedResponse) -> Result<()> {
+        let new_root = base64_array(&proof.root)?;
+        let new_state = VerifiedState {
+            tx_id: proof.tx_id,
+            root: new_root,
+        };
+
+        let leaf = leaf_hash(key, value);
+        if !proof.inclusion_proof.verify(leaf, new_root) {
+            return Err(ProofError::Inclusion { tx_id: proof.tx_id }.into());
+        }
+
+        let mut state = self.verified_state.lock().await;
+        if let Some(trusted) = *state {
+            let dual_proof = proof
+                .dual_proof
+                .as_ref()
+                .ok_or(ProofError::Consistency {
+                    from_tx: trusted.tx_id,
+                    to_tx: new_state.tx_id,
+                })?;
+            if !dual_proof.verify(trusted, new_state) {
+                return Err(ProofError::Consistency {
+                    from_tx: trusted.tx_id,
+                    to_tx: new_state.tx_id,
+                }
+                .into());
+            }
+        }
+        *state = Some(new_state);
+        Ok(())
     }
 }
 
@@ -136,57 +268,344 @@ where
     serializer.serialize_str(&base64::encode(string))
 }
 
+fn base64_array(encoded: &str) -> Result<[u8; 32]> {
+    base64::decode(encoded)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("expected a 32-byte hash"))
+}
+
+fn leaf_hash(key: &[u8], value: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(key);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The last transaction id and merkle root this client has verified; the
+/// trust anchor for the next consistency proof.
+#[derive(Debug, Clone, Copy)]
+struct VerifiedState {
+    tx_id: u64,
+    root: [u8; 32],
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifiedResponse {
+    #[serde(rename = "txId")]
+    tx_id: u64,
+    root: String,
+    #[serde(rename = "inclusionProof")]
+    inclusion_proof: InclusionProof,
+    #[serde(rename = "dualProof")]
+    dual_proof: Option<DualProof>,
+    value: Option<String>,
+}
+
+/// Audit path proving a leaf is included in the tree at the reported root,
+/// shaped like immudb's `inclusionProof`.
+#[derive(Debug, Deserialize)]
+struct InclusionProof {
+    leaf: u64,
+    width: u64,
+    terms: Vec<String>,
+}
+
+impl InclusionProof {
+    fn verify(&self, leaf: [u8; 32], root: [u8; 32]) -> bool {
+        let mut hash = leaf;
+        let mut index = self.leaf;
+        let mut width = self.width;
+        for term in &self.terms {
+            let sibling = match base64_array(term) {
+                Ok(sibling) => sibling,
+                Err(_) => return false,
+            };
+            hash = if index % 2 == 0 && index != width - 1 {
+                node_hash(&hash, &sibling)
+            } else {
+                node_hash(&sibling, &hash)
+            };
+            index /= 2;
+            width = (width + 1) / 2;
+        }
+        hash == root
+    }
+}
+
+/// Proof that `to`'s root is a consistent extension of `from`'s root.
+/// Shaped after immudb's `dualProof.linearProof`. immudb falls back to a
+/// Merkle-interleaved `consistencyProof` once the tx gap is too wide for a
+/// linear chain, which this does not implement, so it only verifies
+/// consistency across a linear run of transactions.
+#[derive(Debug, Deserialize)]
+struct DualProof {
+    #[serde(rename = "linearProof")]
+    linear_proof: LinearProof,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinearProof {
+    #[serde(rename = "sourceTxId")]
+    source_tx_id: u64,
+    #[serde(rename = "targetTxId")]
+    target_tx_id: u64,
+    terms: Vec<String>,
+}
+
+impl DualProof {
+    fn verify(&self, from: VerifiedState, to: VerifiedState) -> bool {
+        let linear = &self.linear_proof;
+        if linear.source_tx_id != from.tx_id || linear.target_tx_id != to.tx_id {
+            return false;
+        }
+        if to.tx_id <= from.tx_id {
+            return false;
+        }
+        let mut hash = from.root;
+        for term in &linear.terms {
+            let sibling = match base64_array(term) {
+                Ok(sibling) => sibling,
+                Err(_) => return false,
+            };
+            hash = node_hash(&hash, &sibling);
+        }
+        hash == to.root
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ProofError {
+    #[error("inclusion proof for tx {tx_id} does not verify against the reported root")]
+    Inclusion { tx_id: u64 },
+    #[error("consistency proof does not chain tx {to_tx} onto trusted tx {from_tx}")]
+    Consistency { from_tx: u64, to_tx: u64 },
+}
+
+/// Watches a set of links for their messages to land, as a Transport-level
+/// complement to `Subscriber::messages()`, not a replacement for it: it
+/// resolves a caller-supplied batch of already-known links instead of
+/// discovering new ones down a branch.
 #[async_trait(?Send)]
-impl Transport<TangleAddress, TangleMessage> for ImmuDB {
-    async fn send_message(&mut self, msg: &TangleMessage) -> iota_streams::core::Result<()> {
-        let msg_index = msg.link().to_msg_index();
-        let response = self
-            .client
-            .post(self.build_url("/db/verified/set"))
-            .json(&json!({"setRequest": {"KVs": [
-                {
-                  "key": base64::encode(&msg_index),
-                     "value": base64::encode(&msg.body)
+trait Subscribe {
+    async fn subscribe(
+        &mut self,
+        links: Vec<TangleAddress>,
+    ) -> Result<impl Stream<Item = TangleMessage>>;
+}
+
+#[async_trait(?Send)]
+impl Subscribe for ImmuDB {
+    /// Retries `recv_message_inner` on each of `links` in turn with capped
+    /// exponential backoff (reset between links), forwarding each resolved
+    /// message over a channel as soon as it lands and de-duplicating by
+    /// `to_msg_index()` in case a link is watched more than once. Goes
+    /// through `recv_message_inner` rather than `Transport`, whose `?Send`
+    /// future `tokio::spawn` rejects.
+    async fn subscribe(
+        &mut self,
+        links: Vec<TangleAddress>,
+    ) -> Result<impl Stream<Item = TangleMessage>> {
+        const MIN_BACKOFF: Duration = Duration::from_millis(100);
+        const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+        let (tx, rx) = flume::unbounded();
+        let mut transport = self.clone();
+        tokio::spawn(async move {
+            let mut seen = HashSet::new();
+            for link in links {
+                if !seen.insert(link.to_msg_index()) {
+                    continue;
                 }
-              ]
-            }}))
-            .send()
+                let mut backoff = MIN_BACKOFF;
+                loop {
+                    match transport.recv_message_inner(&link).await {
+                        Ok(msg) => {
+                            let _ = tx.send_async(msg).await;
+                            break;
+                        }
+                        Err(_) => {
+                            tokio::time::sleep(backoff).await;
+                            backoff = (backoff * 2).min(MAX_BACKOFF);
+                        }
+                    }
+                }
+            }
+        });
+        Ok(rx.into_stream())
+    }
+}
+
+/// Prefix every message of a channel shares, so a single `/db/scan` can
+/// pull the whole channel back without knowing any of its links upfront.
+fn channel_prefix(announcement: &TangleAddress) -> Vec<u8> {
+    format!("channel:{}/", announcement.appinst()).into_bytes()
+}
+
+/// The structured key a `TangleMessage` is stored under: channel prefix
+/// followed by the message's own `to_msg_index()`.
+fn message_key(link: &TangleAddress) -> Vec<u8> {
+    let mut key = channel_prefix(link);
+    key.extend(link.to_msg_index());
+    key
+}
+
+/// Key under which a message's resolved branch id (see `resolve_branch_id`)
+/// is cached, so a descendant only needs one lookup to inherit it.
+fn branch_id_cache_key(link: &TangleAddress) -> Vec<u8> {
+    let mut key = channel_prefix(link);
+    key.extend(b"branch-of:");
+    key.extend(link.to_msg_index());
+    key
+}
+
+/// Prefix shared by every message resolved to `branch_id`, for
+/// `recv_branch`'s `/db/scan`. `channel` only needs to share the target
+/// channel's `appinst`, not be the channel's announcement itself.
+fn branch_prefix(channel: &TangleAddress, branch_id: &[u8]) -> Vec<u8> {
+    let mut key = channel_prefix(channel);
+    key.extend(b"branch:");
+    key.extend(branch_id);
+    key.push(b'/');
+    key
+}
+
+/// Secondary-index key pointing from a branch-scoped position to the
+/// message's primary `message_key`, so `recv_branch` can scan one branch
+/// without touching another's messages.
+fn branch_key(channel: &TangleAddress, branch_id: &[u8], link: &TangleAddress) -> Vec<u8> {
+    let mut key = branch_prefix(channel, branch_id);
+    key.extend(link.to_msg_index());
+    key
+}
+
+impl ImmuDB {
+    // `Transport`'s `?Send` bound (like the rest of this file) erases
+    // these into a non-`Send` boxed future, which `tokio::spawn` (used by
+    // `Subscribe::subscribe` above) rejects on the default multi-thread
+    // runtime. Keeping the real bodies as plain inherent methods, and
+    // having the trait impl below just delegate to them, gives callers
+    // that need a `Send` future (like `subscribe`) something to call
+    // directly instead.
+    async fn send_message_inner(&mut self, msg: &TangleMessage) -> Result<()> {
+        let key = message_key(msg.link());
+        let response = self
+            .db
+            .post(
+                "/db/verified/set",
+                json!({"setRequest": {"KVs": [
+                    {
+                      "key": base64::encode(&key),
+                         "value": base64::encode(&msg.body)
+                    }
+                  ]
+                }}),
+            )
             .await?;
         // println!("send response: {response}");
-        if response.status().is_success() {
+        let status = response.status();
+        if status.is_success() {
+            let proof: VerifiedResponse = response.json().await?;
+            self.accept_verified(&key, &msg.body, proof).await?;
+            self.index_branch(msg, &key).await?;
             Ok(())
         } else {
-            anyhow::bail!("error sending message to {msg_index}");
+            anyhow::bail!("error sending message to {}", msg.link());
         }
     }
 
-    async fn recv_message(
-        &mut self,
-        link: &TangleAddress,
-    ) -> iota_streams::core::Result<TangleMessage> {
+    /// Resolves `msg`'s branch id (see `resolve_branch_id`) and records it
+    /// under `branch_key`/`branch_id_cache_key`, so `recv_branch` can later
+    /// scan this message without touching other branches of the channel.
+    /// Bookkeeping only, so it uses immudb's plain (non-`verified`) KV ops:
+    /// losing it degrades `recv_branch` convenience, not message integrity.
+    async fn index_branch(&mut self, msg: &TangleMessage, key: &[u8]) -> Result<()> {
+        let branch_id = self.resolve_branch_id(msg).await?;
+        let link = msg.link();
+        self.db
+            .post(
+                "/db/set",
+                json!({"setRequest": {"KVs": [
+                    {
+                        "key": base64::encode(branch_id_cache_key(link)),
+                        "value": base64::encode(&branch_id)
+                    },
+                    {
+                        "key": base64::encode(branch_key(link, &branch_id, link)),
+                        "value": base64::encode(key)
+                    }
+                ]}}),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// A message's branch id is its oldest ancestor's own link index. `msg`
+    /// itself is that ancestor when it has no real predecessor (the root of
+    /// a channel or branch, linked from `TangleAddress::default()`);
+    /// otherwise it inherits whatever was cached for `msg.prev_link()`, or
+    /// (first descendant of an as-yet-unindexed predecessor) treats that
+    /// predecessor as the root.
+    async fn resolve_branch_id(&mut self, msg: &TangleMessage) -> Result<Vec<u8>> {
+        let prev = *msg.prev_link();
+        if prev.to_msg_index() == TangleAddress::default().to_msg_index() {
+            return Ok(msg.link().to_msg_index());
+        }
         let response = self
-            .client
-            .post(self.build_url("/db/verified/get"))
-            .json(&json!({
-                "keyRequest": {
-                  "key": base64::encode(link.to_msg_index())
-                }
-            }))
-            .send()
+            .db
+            .post(
+                "/db/get",
+                json!({"keyRequest": {"key": base64::encode(branch_id_cache_key(&prev))}}),
+            )
+            .await?;
+        if response.status().is_success() {
+            #[derive(Deserialize)]
+            struct GetResponse {
+                value: Option<String>,
+            }
+            let body: GetResponse = response.json().await?;
+            if let Some(value) = body.value {
+                return base64::decode(value).map_err(Into::into);
+            }
+        }
+        Ok(prev.to_msg_index())
+    }
+
+    async fn recv_message_inner(&mut self, link: &TangleAddress) -> Result<TangleMessage> {
+        let key = message_key(link);
+        let response = self
+            .db
+            .post(
+                "/db/verified/get",
+                json!({
+                    "keyRequest": {
+                      "key": base64::encode(&key)
+                    }
+                }),
+            )
             .await?;
         let status = response.status();
         if status.is_success() {
-            let payload: serde_json::Value = response.json().await?;
-            // println!("recv response: {payload}");
+            let proof: VerifiedResponse = response.json().await?;
+            let value = base64::decode(
+                proof
+                    .value
+                    .as_deref()
+                    .expect("getting message from successful recv response"),
+            )?;
+            self.accept_verified(&key, &value, proof).await?;
             Ok(TangleMessage::new(
                 *link,
                 TangleAddress::default(),
-                base64::decode(
-                    payload["value"]
-                        .as_str()
-                        .expect("getting message from successful recv response"),
-                )?
-                .into(),
+                value.into(),
             ))
         } else {
             // println!("recv error response: {}", response.text().await?);
@@ -194,3 +613,323 @@ impl Transport<TangleAddress, TangleMessage> for ImmuDB {
         }
     }
 }
+
+#[async_trait(?Send)]
+impl Transport<TangleAddress, TangleMessage> for ImmuDB {
+    async fn send_message(&mut self, msg: &TangleMessage) -> iota_streams::core::Result<()> {
+        Ok(self.send_message_inner(msg).await?)
+    }
+
+    async fn recv_message(
+        &mut self,
+        link: &TangleAddress,
+    ) -> iota_streams::core::Result<TangleMessage> {
+        Ok(self.recv_message_inner(link).await?)
+    }
+}
+
+impl ImmuDB {
+    /// Pulls every message of `announcement`'s branch back in one ranged
+    /// `/db/scan` over the branch's secondary index (see `branch_key`),
+    /// instead of chasing `last_msg_link` one `recv_message` at a time.
+    /// Scoped to `announcement`'s own branch, not the whole channel: other
+    /// branches are indexed under a different `branch_id` and don't match
+    /// this prefix. Since `/db/scan` isn't itself proof-verified by immudb,
+    /// each entry is re-fetched through `/db/verified/get` and checked with
+    /// `accept_verified` before being returned, so this is no less
+    /// tamper-evident than `recv_message`.
+    async fn recv_branch(&mut self, announcement: &TangleAddress) -> Result<Vec<TangleMessage>> {
+        #[derive(Deserialize)]
+        struct ScanEntry {
+            value: String,
+        }
+        #[derive(Deserialize)]
+        struct ScanResponse {
+            entries: Vec<ScanEntry>,
+        }
+
+        let branch_id = announcement.to_msg_index();
+        let response = self
+            .db
+            .post(
+                "/db/scan",
+                json!({
+                    "scanRequest": {
+                        "prefix": base64::encode(branch_prefix(announcement, &branch_id))
+                    }
+                }),
+            )
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("error scanning branch of {announcement}");
+        }
+        let scan: ScanResponse = response.json().await?;
+
+        let mut messages = Vec::with_capacity(scan.entries.len());
+        for entry in scan.entries {
+            let key = base64::decode(entry.value)?;
+            let response = self
+                .db
+                .post(
+                    "/db/verified/get",
+                    json!({"keyRequest": {"key": base64::encode(&key)}}),
+                )
+                .await?;
+            if !response.status().is_success() {
+                anyhow::bail!("error fetching branch message for key {key:?}");
+            }
+            let proof: VerifiedResponse = response.json().await?;
+            let value = base64::decode(
+                proof
+                    .value
+                    .as_deref()
+                    .expect("getting message from successful recv response"),
+            )?;
+            self.accept_verified(&key, &value, proof).await?;
+            messages.push(TangleMessage::new(
+                *announcement,
+                TangleAddress::default(),
+                value.into(),
+            ));
+        }
+        Ok(messages)
+    }
+}
+
+/// Wraps a `Transport` with an in-memory cache of `recv_message` results,
+/// keyed by `to_msg_index()`, and a `prefetch` that warms the cache for a
+/// batch of links concurrently.
+#[derive(Clone)]
+struct CachingTransport<T> {
+    inner: T,
+    cache: Arc<Mutex<HashMap<Vec<u8>, TangleMessage>>>,
+}
+
+impl<T> CachingTransport<T>
+where
+    T: Transport<TangleAddress, TangleMessage> + Clone,
+{
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn prefetch(&self, links: &[TangleAddress]) -> Result<()> {
+        let mut fetches: FuturesUnordered<_> = links
+            .iter()
+            .map(|link| {
+                let mut transport = self.inner.clone();
+                let link = *link;
+                async move { (link, transport.recv_message(&link).await) }
+            })
+            .collect();
+        while let Some((link, result)) = fetches.next().await {
+            if let Ok(msg) = result {
+                self.cache.lock().await.insert(link.to_msg_index(), msg);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<T> Transport<TangleAddress, TangleMessage> for CachingTransport<T>
+where
+    T: Transport<TangleAddress, TangleMessage> + Clone,
+{
+    async fn send_message(&mut self, msg: &TangleMessage) -> iota_streams::core::Result<()> {
+        self.inner.send_message(msg).await
+    }
+
+    async fn recv_message(
+        &mut self,
+        link: &TangleAddress,
+    ) -> iota_streams::core::Result<TangleMessage> {
+        let key = link.to_msg_index();
+        if let Some(msg) = self.cache.lock().await.get(&key) {
+            return Ok(msg.clone());
+        }
+        let msg = self.inner.recv_message(link).await?;
+        self.cache.lock().await.insert(key, msg.clone());
+        Ok(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b64(bytes: [u8; 32]) -> String {
+        base64::encode(bytes)
+    }
+
+    // A balanced 4-leaf tree over (k0,v0)..(k3,v3):
+    //       root
+    //      /    \
+    //    n01    n23
+    //   /  \    /  \
+    //  l0  l1  l2  l3
+    struct Tree {
+        leaves: [[u8; 32]; 4],
+        n01: [u8; 32],
+        n23: [u8; 32],
+        root: [u8; 32],
+    }
+
+    fn tree() -> Tree {
+        let leaves = [
+            leaf_hash(b"k0", b"v0"),
+            leaf_hash(b"k1", b"v1"),
+            leaf_hash(b"k2", b"v2"),
+            leaf_hash(b"k3", b"v3"),
+        ];
+        let n01 = node_hash(&leaves[0], &leaves[1]);
+        let n23 = node_hash(&leaves[2], &leaves[3]);
+        let root = node_hash(&n01, &n23);
+        Tree {
+            leaves,
+            n01,
+            n23,
+            root,
+        }
+    }
+
+    #[test]
+    fn inclusion_proof_verifies_each_leaf() {
+        let t = tree();
+        let proofs = [
+            (0u64, vec![t.leaves[1], t.n23]),
+            (1u64, vec![t.leaves[0], t.n23]),
+            (2u64, vec![t.leaves[3], t.n01]),
+            (3u64, vec![t.leaves[2], t.n01]),
+        ];
+        for (leaf, terms) in proofs {
+            let proof = InclusionProof {
+                leaf,
+                width: 4,
+                terms: terms.into_iter().map(b64).collect(),
+            };
+            assert!(proof.verify(t.leaves[leaf as usize], t.root));
+        }
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_tampered_leaf() {
+        let t = tree();
+        let proof = InclusionProof {
+            leaf: 0,
+            width: 4,
+            terms: vec![b64(t.leaves[1]), b64(t.n23)],
+        };
+        assert!(!proof.verify(leaf_hash(b"k0", b"tampered"), t.root));
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_tampered_root() {
+        let t = tree();
+        let proof = InclusionProof {
+            leaf: 0,
+            width: 4,
+            terms: vec![b64(t.leaves[1]), b64(t.n23)],
+        };
+        assert!(!proof.verify(t.leaves[0], t.n01));
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_tampered_term() {
+        let t = tree();
+        let proof = InclusionProof {
+            leaf: 0,
+            width: 4,
+            terms: vec![b64(t.leaves[2]), b64(t.n23)],
+        };
+        assert!(!proof.verify(t.leaves[0], t.root));
+    }
+
+    #[test]
+    fn dual_proof_verifies_consistent_extension() {
+        let t = tree();
+        let from = VerifiedState {
+            tx_id: 1,
+            root: t.n01,
+        };
+        let to = VerifiedState {
+            tx_id: 2,
+            root: t.root,
+        };
+        let proof = DualProof {
+            linear_proof: LinearProof {
+                source_tx_id: 1,
+                target_tx_id: 2,
+                terms: vec![b64(t.n23)],
+            },
+        };
+        assert!(proof.verify(from, to));
+    }
+
+    #[test]
+    fn dual_proof_rejects_rollback() {
+        let t = tree();
+        let from = VerifiedState {
+            tx_id: 2,
+            root: t.root,
+        };
+        let to = VerifiedState {
+            tx_id: 1,
+            root: t.n01,
+        };
+        let proof = DualProof {
+            linear_proof: LinearProof {
+                source_tx_id: 2,
+                target_tx_id: 1,
+                terms: vec![b64(t.n23)],
+            },
+        };
+        assert!(!proof.verify(from, to));
+    }
+
+    #[test]
+    fn dual_proof_rejects_forked_history() {
+        let t = tree();
+        let from = VerifiedState {
+            tx_id: 1,
+            root: t.n01,
+        };
+        let to = VerifiedState {
+            tx_id: 2,
+            root: t.root,
+        };
+        let forked_n23 = node_hash(&t.leaves[2], &leaf_hash(b"k3", b"forked"));
+        let proof = DualProof {
+            linear_proof: LinearProof {
+                source_tx_id: 1,
+                target_tx_id: 2,
+                terms: vec![b64(forked_n23)],
+            },
+        };
+        assert!(!proof.verify(from, to));
+    }
+
+    #[test]
+    fn dual_proof_rejects_mismatched_tx_ids() {
+        let t = tree();
+        let from = VerifiedState {
+            tx_id: 1,
+            root: t.n01,
+        };
+        let to = VerifiedState {
+            tx_id: 2,
+            root: t.root,
+        };
+        let proof = DualProof {
+            linear_proof: LinearProof {
+                source_tx_id: 1,
+                target_tx_id: 3,
+                terms: vec![b64(t.n23)],
+            },
+        };
+        assert!(!proof.verify(from, to));
+    }
+}